@@ -0,0 +1,248 @@
+//! Emits the knight/king/pawn attack tables and the rook/bishop magic
+//! bitboard tables consulted by the attack functions in `rust_search.rs`.
+//! Squares are numbered 0..63 as `rank * 8 + file`, matching `BoardArray`.
+//! Doing this once at build time means the exhaustive search never
+//! recomputes an offset, blocker scan, or magic number on the hot path.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (2, 1), (1, 2), (-1, 2), (-2, 1),
+    (-2, -1), (-1, -2), (1, -2), (2, -1),
+];
+const KING_OFFSETS: [(i32, i32); 8] = [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+];
+const ROOK_DIRS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+fn offset_table(offsets: &[(i32, i32); 8]) -> [u64; 64] {
+    let mut table = [0u64; 64];
+    for (square, bb) in table.iter_mut().enumerate() {
+        let x = (square % 8) as i32;
+        let y = (square / 8) as i32;
+        for &(dx, dy) in offsets {
+            let nx = x + dx;
+            let ny = y + dy;
+            if (0..8).contains(&nx) && (0..8).contains(&ny) {
+                *bb |= 1u64 << (ny * 8 + nx);
+            }
+        }
+    }
+    table
+}
+
+fn pawn_table(dy_dir: i32) -> [u64; 64] {
+    let mut table = [0u64; 64];
+    for (square, bb) in table.iter_mut().enumerate() {
+        let x = (square % 8) as i32;
+        let y = (square / 8) as i32;
+        for dx in [-1, 1] {
+            let nx = x + dx;
+            let ny = y + dy_dir;
+            if (0..8).contains(&nx) && (0..8).contains(&ny) {
+                *bb |= 1u64 << (ny * 8 + nx);
+            }
+        }
+    }
+    table
+}
+
+/// The occupancy bits that can possibly change a slider's attack set from
+/// `square`: every square along its rays, excluding the board edge (a piece
+/// sitting on the far edge is never "jumped", so its presence is irrelevant).
+fn relevant_mask(square: usize, dirs: &[(i32, i32); 4]) -> u64 {
+    let x0 = (square % 8) as i32;
+    let y0 = (square / 8) as i32;
+    let mut mask = 0u64;
+    for &(dx, dy) in dirs {
+        let mut x = x0 + dx;
+        let mut y = y0 + dy;
+        while (0..8).contains(&x) && (0..8).contains(&y) {
+            let nx = x + dx;
+            let ny = y + dy;
+            if (0..8).contains(&nx) && (0..8).contains(&ny) {
+                mask |= 1u64 << (y * 8 + x);
+            }
+            x = nx;
+            y = ny;
+        }
+    }
+    mask
+}
+
+/// The actual attack set from `square` given a full board occupancy, stopping
+/// (inclusive) at the first occupied square in each direction.
+fn sliding_attacks(square: usize, occupied: u64, dirs: &[(i32, i32); 4]) -> u64 {
+    let x0 = (square % 8) as i32;
+    let y0 = (square / 8) as i32;
+    let mut attacks = 0u64;
+    for &(dx, dy) in dirs {
+        let mut x = x0 + dx;
+        let mut y = y0 + dy;
+        while (0..8).contains(&x) && (0..8).contains(&y) {
+            let bit = 1u64 << (y * 8 + x);
+            attacks |= bit;
+            if occupied & bit != 0 {
+                break;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+    attacks
+}
+
+/// Every subset of `mask`, via the standard carry-rippler trick.
+fn subsets_of(mask: u64) -> Vec<u64> {
+    let mut subsets = Vec::new();
+    let mut subset = 0u64;
+    loop {
+        subsets.push(subset);
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == 0 {
+            break;
+        }
+    }
+    subsets
+}
+
+/// A tiny deterministic xorshift64* PRNG, seeded once per call site, so the
+/// magic numbers below are reproducible across builds.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// A sparse candidate magic: ANDing a few random draws biases toward few set bits,
+    /// which empirically tends to make good magics easier to find.
+    fn sparse_candidate(&mut self) -> u64 {
+        self.next_u64() & self.next_u64() & self.next_u64()
+    }
+}
+
+struct MagicTable {
+    masks: [u64; 64],
+    magics: [u64; 64],
+    shifts: [u32; 64],
+    offsets: [usize; 64],
+    flat: Vec<u64>,
+}
+
+/// Find a magic multiplier per square and build the flat attack table for a slider.
+fn build_magics(dirs: &[(i32, i32); 4], seed: u64) -> MagicTable {
+    let mut rng = Rng(seed);
+    let mut masks = [0u64; 64];
+    let mut magics = [0u64; 64];
+    let mut shifts = [0u32; 64];
+    let mut offsets = [0usize; 64];
+    let mut flat = Vec::new();
+
+    for square in 0..64 {
+        let mask = relevant_mask(square, dirs);
+        let bits = mask.count_ones();
+        let shift = 64 - bits;
+        let occupancies = subsets_of(mask);
+        let attacks: Vec<u64> = occupancies
+            .iter()
+            .map(|&occ| sliding_attacks(square, occ, dirs))
+            .collect();
+
+        let table_size = 1usize << bits;
+        let mut table = vec![None; table_size];
+        let magic = loop {
+            let candidate = rng.sparse_candidate();
+            table.iter_mut().for_each(|slot| *slot = None);
+            let mut ok = true;
+            for (&occ, &attack) in occupancies.iter().zip(attacks.iter()) {
+                let index = ((occ.wrapping_mul(candidate)) >> shift) as usize;
+                match table[index] {
+                    None => table[index] = Some(attack),
+                    Some(existing) if existing == attack => {}
+                    Some(_) => {
+                        ok = false;
+                        break;
+                    }
+                }
+            }
+            if ok {
+                break candidate;
+            }
+        };
+
+        masks[square] = mask;
+        magics[square] = magic;
+        shifts[square] = shift;
+        offsets[square] = flat.len();
+        for slot in table {
+            flat.push(slot.unwrap_or(0));
+        }
+    }
+
+    MagicTable { masks, magics, shifts, offsets, flat }
+}
+
+fn write_u64_array(out: &mut String, name: &str, table: &[u64; 64]) {
+    let entries: Vec<String> = table.iter().map(|v| format!("{v}")).collect();
+    let _ = writeln!(out, "pub const {name}: [u64; 64] = [{}];", entries.join(", "));
+}
+
+fn write_u32_array(out: &mut String, name: &str, table: &[u32; 64]) {
+    let entries: Vec<String> = table.iter().map(|v| format!("{v}")).collect();
+    let _ = writeln!(out, "pub const {name}: [u32; 64] = [{}];", entries.join(", "));
+}
+
+fn write_usize_array(out: &mut String, name: &str, table: &[usize; 64]) {
+    let entries: Vec<String> = table.iter().map(|v| format!("{v}")).collect();
+    let _ = writeln!(out, "pub const {name}: [usize; 64] = [{}];", entries.join(", "));
+}
+
+fn write_magic_table(out: &mut String, prefix: &str, magic: &MagicTable) {
+    write_u64_array(out, &format!("{prefix}_MASKS"), &magic.masks);
+    write_u64_array(out, &format!("{prefix}_MAGICS"), &magic.magics);
+    write_u32_array(out, &format!("{prefix}_SHIFTS"), &magic.shifts);
+    write_usize_array(out, &format!("{prefix}_OFFSETS"), &magic.offsets);
+    // `static`, not `const`: these flat attack tables run into the tens of
+    // thousands of entries, and clippy's large_const_arrays flags a const
+    // that size as one copy-per-use away from bloating the binary.
+    let entries: Vec<String> = magic.flat.iter().map(|v| format!("{v}")).collect();
+    let _ = writeln!(
+        out,
+        "pub static {prefix}_TABLE: [u64; {}] = [{}];",
+        magic.flat.len(),
+        entries.join(", ")
+    );
+}
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let dest = Path::new(&out_dir).join("attack_tables.rs");
+
+    let knight = offset_table(&KNIGHT_OFFSETS);
+    let king = offset_table(&KING_OFFSETS);
+    let white_pawn = pawn_table(1);
+    let black_pawn = pawn_table(-1);
+    let rook_magics = build_magics(&ROOK_DIRS, 0x9E3779B97F4A7C15);
+    let bishop_magics = build_magics(&BISHOP_DIRS, 0xC2B2AE3D27D4EB4F);
+
+    let mut out = String::new();
+    out.push_str("// Generated by build.rs - do not edit by hand.\n");
+    write_u64_array(&mut out, "KNIGHT_ATTACKS", &knight);
+    write_u64_array(&mut out, "KING_ATTACKS", &king);
+    write_u64_array(&mut out, "WHITE_PAWN_ATTACKS", &white_pawn);
+    write_u64_array(&mut out, "BLACK_PAWN_ATTACKS", &black_pawn);
+    write_magic_table(&mut out, "ROOK", &rook_magics);
+    write_magic_table(&mut out, "BISHOP", &bishop_magics);
+
+    fs::write(&dest, out).expect("failed to write attack_tables.rs");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -1,6 +1,8 @@
 use rayon::prelude::*;
 use std::cmp::{max, min};
+use std::collections::HashSet;
 use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
 use std::thread;
 use std::io::Write;
 
@@ -10,6 +12,11 @@ use std::io::Write;
 /// where file 0 corresponds to “a” and rank 1 is the bottom row.
 type BoardArray = [Option<char>; 64];
 
+// Knight/king/pawn attack tables and rook/bishop magic bitboard tables, emitted
+// by build.rs for every square 0..63 so the bitboard attack functions below
+// never recompute an offset or direction walk at runtime.
+include!(concat!(env!("OUT_DIR"), "/attack_tables.rs"));
+
 /// Convert (file, rank) into an index. Here rank is 1-indexed and file is 0-indexed.
 fn pos_to_index(x: u8, y: u8) -> usize {
     ((y - 1) as usize) * 8 + (x as usize)
@@ -108,33 +115,113 @@ fn generate_combinations(num_pairs: usize) -> Vec<Vec<char>> {
     results
 }
 
+/// A single "stale" rule: given a board and its already-derived `BitBoards`,
+/// true means the board is rejected.
+type FilterPredicate = Box<dyn Fn(&BoardArray, &BitBoards) -> bool + Send + Sync>;
+
+/// One named, toggleable rule in a `FilterSet`. Returning true means the
+/// board is rejected: it fails this particular "stale" predicate.
+struct Filter {
+    name: &'static str,
+    predicate: FilterPredicate,
+}
+
+/// A user-selectable chain of board predicates, driving what "stale" means
+/// for a given run. `search` rejects a board as soon as any active filter
+/// rejects it, so `process_combination` can be reused as a library entry
+/// point with a caller-supplied definition instead of a hardcoded chain.
+struct FilterSet {
+    filters: Vec<Filter>,
+}
+
+impl FilterSet {
+    /// The filter chain this crate always shipped with: no piece under attack,
+    /// White can't deliver check, no piece is left en prise, neither side has
+    /// a passed pawn, and White's pawns span at most `max_pawn_islands` islands.
+    fn default_with_threshold(max_pawn_islands: u32) -> FilterSet {
+        FilterSet {
+            filters: vec![
+                Filter { name: "piece-under-attack", predicate: Box::new(is_piece_under_attack) },
+                Filter { name: "can-deliver-check", predicate: Box::new(can_deliver_check) },
+                Filter { name: "en-prise", predicate: Box::new(|_board, bb| has_piece_en_prise(bb)) },
+                Filter { name: "passed-pawn", predicate: Box::new(|board, _bb| has_passed_pawn(board)) },
+                Filter {
+                    name: "pawn-islands",
+                    predicate: Box::new(move |board, _bb| count_white_pawn_islands(board) > max_pawn_islands),
+                },
+            ],
+        }
+    }
+
+    /// Names of every filter in this set, in evaluation order.
+    fn names(&self) -> Vec<&'static str> {
+        self.filters.iter().map(|f| f.name).collect()
+    }
+
+    /// Keep only the named filters, preserving their default order. An empty
+    /// `names` list means "keep everything" (the CLI's default). Panics if a
+    /// name doesn't match any filter, rather than silently degrading to an
+    /// empty (so unfiltered) set.
+    fn select(self, names: &[String]) -> FilterSet {
+        if names.is_empty() {
+            return self;
+        }
+        for name in names {
+            if !self.filters.iter().any(|f| f.name == name.as_str()) {
+                panic!(
+                    "Unrecognized filter name: {name} (available: {})",
+                    self.names().join(", ")
+                );
+            }
+        }
+        FilterSet {
+            filters: self
+                .filters
+                .into_iter()
+                .filter(|f| names.iter().any(|n| n == f.name))
+                .collect(),
+        }
+    }
+
+    /// True if any active filter rejects the board. Builds the board's
+    /// `BitBoards` once and shares it across every filter in the chain,
+    /// instead of each filter rescanning the board for its own copy.
+    fn rejects(&self, board: &BoardArray) -> bool {
+        let bb = BitBoards::from_board(board);
+        self.filters.iter().any(|f| (f.predicate)(board, &bb))
+    }
+
+    /// The name of the first active filter that rejects the board, if any.
+    fn first_rejecting_name(&self, board: &BoardArray) -> Option<&'static str> {
+        let bb = BitBoards::from_board(board);
+        self.filters.iter().find(|f| (f.predicate)(board, &bb)).map(|f| f.name)
+    }
+}
+
 /// The backtracking search. For the given (ordered) placement options (one vector per piece pair),
 /// choose one placement per pair so that no two pieces share a square. When a complete board is built,
-/// run the filters and, if it qualifies, send the FEN to the provided sender.
+/// run `filters` and, if it qualifies, send the FEN to the provided sender.
 fn search(
     index: usize,
     options: &Vec<Vec<Placement>>,
     occupied: &mut [bool; 64],
     current: &mut Vec<Placement>,
     sender: &Sender<String>,
+    seen: &SeenBoards,
+    filters: &FilterSet,
 ) {
     if index == options.len() {
         let board = build_board(current);
 
-        // Filter: exactly one black king.
+        // Invariant: a legal "stale" board has exactly one black king.
         if board.iter().filter(|&&sq| sq == Some('k')).count() != 1 {
             return;
         }
-        if is_piece_under_attack(&board) {
+        if filters.rejects(&board) {
             return;
         }
-        if can_deliver_check(&board) {
-            return;
-        }
-        if has_passed_pawn(&board) {
-            return;
-        }
-        if count_white_pawn_islands(&board) > 1 {
+        // Skip boards transposed/mirrored into from a different placement order.
+        if !seen.insert(zobrist_hash(&board)) {
             return;
         }
         let fen = board_to_fen(&board);
@@ -151,7 +238,7 @@ fn search(
         occupied[white_index] = true;
         occupied[black_index] = true;
         current.push(*placement);
-        search(index + 1, options, occupied, current, sender);
+        search(index + 1, options, occupied, current, sender, seen, filters);
         current.pop();
         occupied[white_index] = false;
         occupied[black_index] = false;
@@ -159,13 +246,19 @@ fn search(
 }
 
 /// Process one combination by building the placement options and launching the backtracking search.
-/// Valid FEN strings are sent immediately via the provided sender.
-fn process_combination(combination: &[char], sender: &Sender<String>) {
+/// Valid, not-yet-seen FEN strings are sent immediately via the provided sender. Reusable as a library
+/// entry point: callers bring their own `FilterSet` and dedup set instead of relying on crate globals.
+fn process_combination(
+    combination: &[char],
+    sender: &Sender<String>,
+    seen: &SeenBoards,
+    filters: &FilterSet,
+) {
     let placements_options: Vec<Vec<Placement>> =
         combination.iter().map(|&p| generate_placements(p)).collect();
     let mut occupied = [false; 64];
     let mut current = Vec::new();
-    search(0, &placements_options, &mut occupied, &mut current, sender);
+    search(0, &placements_options, &mut occupied, &mut current, sender, seen, filters);
 }
 
 /// Build a board (an array of 64 Option<char>) from the list of placements.
@@ -207,6 +300,64 @@ fn board_to_fen(board: &BoardArray) -> String {
     fen_rows.join("/") + " w - - 0 1"
 }
 
+/// Why `fen_to_board` rejected a piece-placement string.
+#[derive(Debug)]
+enum FenError {
+    /// The placement field did not split into exactly 8 ranks on `/`.
+    WrongRankCount(usize),
+    /// One rank's digit run-lengths and piece letters didn't add up to 8 files.
+    RankWrongLength { rank: usize, length: usize },
+    /// A character wasn't a digit 1-8 or one of `PNBRQKpnbrqk`.
+    InvalidChar(char),
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::WrongRankCount(n) => write!(f, "expected 8 ranks, found {n}"),
+            FenError::RankWrongLength { rank, length } => {
+                write!(f, "rank {rank} has {length} files, expected 8")
+            }
+            FenError::InvalidChar(c) => write!(f, "invalid FEN character '{c}'"),
+        }
+    }
+}
+
+/// Parse a FEN's piece-placement field (everything up to the first space, or
+/// the whole string if there is none) back into a `BoardArray`. The inverse
+/// of `board_to_fen`, so a generated `.fen` file can be re-ingested by this
+/// same tool.
+fn fen_to_board(fen: &str) -> Result<BoardArray, FenError> {
+    let placement = fen.split_whitespace().next().unwrap_or("");
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+    let mut board: BoardArray = [None; 64];
+    for (rank_index, rank_str) in ranks.iter().enumerate() {
+        // FEN lists rank 8 first; BoardArray indexes rank 1 as row 0.
+        let rank = 7 - rank_index;
+        let mut file = 0usize;
+        for c in rank_str.chars() {
+            if let Some(run) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                file += run as usize;
+            } else if "PNBRQKpnbrqk".contains(c) {
+                if file >= 8 {
+                    return Err(FenError::RankWrongLength { rank: rank + 1, length: file + 1 });
+                }
+                board[rank * 8 + file] = Some(c);
+                file += 1;
+            } else {
+                return Err(FenError::InvalidChar(c));
+            }
+        }
+        if file != 8 {
+            return Err(FenError::RankWrongLength { rank: rank + 1, length: file });
+        }
+    }
+    Ok(board)
+}
+
 /// --- Minimal Chess Functions for Filtering ---
 
 /// Returns the piece (if any) at board cell (x,y); (x,y) are 0-indexed.
@@ -217,125 +368,245 @@ fn get_piece_at(board: &BoardArray, x: i32, y: i32) -> Option<char> {
     board[(y as usize) * 8 + (x as usize)]
 }
 
-/// Given two coordinates “from” and “to” and a piece, test whether that piece “attacks” the destination.
-/// For sliding pieces the path must be clear.
-fn piece_attacks(board: &BoardArray, from: (i32, i32), to: (i32, i32), piece: char) -> bool {
-    let dx = to.0 - from.0;
-    let dy = to.1 - from.1;
-    match piece.to_ascii_lowercase() {
-        'p' => {
-            // Pawns attack diagonally.
-            if piece.is_uppercase() {
-                (dx == -1 && dy == 1) || (dx == 1 && dy == 1)
-            } else {
-                (dx == -1 && dy == -1) || (dx == 1 && dy == -1)
-            }
-        }
-        'n' => {
-            let knight_moves = [
-                (2, 1), (1, 2), (-1, 2), (-2, 1),
-                (-2, -1), (-1, -2), (1, -2), (2, -1)
-            ];
-            knight_moves.iter().any(|&(mx, my)| dx == mx && dy == my)
-        }
-        'k' => (dx.abs() <= 1 && dy.abs() <= 1) && (dx != 0 || dy != 0),
-        'b' => {
-            if dx.abs() == dy.abs() && dx != 0 {
-                let step_x = dx.signum();
-                let step_y = dy.signum();
-                let mut x = from.0 + step_x;
-                let mut y = from.1 + step_y;
-                while (x, y) != to {
-                    if get_piece_at(board, x, y).is_some() {
-                        return false;
-                    }
-                    x += step_x;
-                    y += step_y;
+/// --- Bitboard core for fast attack filtering ---
+///
+/// Each piece kind gets its own 64-bit occupancy bitboard (bit `i` set means a
+/// piece of that kind sits on board index `i`, using the same layout as
+/// `BoardArray`). `BitBoards` aggregates the twelve per-kind-per-color boards
+/// plus `white_occupied`/`black_occupied`/`all_occupied`, so "is anything
+/// attacked" becomes a handful of bit operations instead of the O(64x64)
+/// scan the old `piece_attacks` required. `build_board`/`board_to_fen` stay
+/// the canonical `BoardArray` converters; `BitBoards::from_board` derives the
+/// bitboard view from them on demand.
+type Bitboard = u64;
+
+const PAWN: usize = 0;
+const KNIGHT: usize = 1;
+const BISHOP: usize = 2;
+const ROOK: usize = 3;
+const QUEEN: usize = 4;
+const KING: usize = 5;
+
+/// Maps a piece's FEN letter (either case) to its index into `BitBoards::white`/`black`.
+fn piece_kind_index(piece: char) -> usize {
+    match piece.to_ascii_uppercase() {
+        'P' => PAWN,
+        'N' => KNIGHT,
+        'B' => BISHOP,
+        'R' => ROOK,
+        'Q' => QUEEN,
+        'K' => KING,
+        _ => panic!("Invalid piece type"),
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BitBoards {
+    white: [Bitboard; 6],
+    black: [Bitboard; 6],
+    white_occupied: Bitboard,
+    black_occupied: Bitboard,
+    all_occupied: Bitboard,
+}
+
+impl BitBoards {
+    /// Derive the bitboard view of a `BoardArray`.
+    fn from_board(board: &BoardArray) -> BitBoards {
+        let mut bb = BitBoards {
+            white: [0; 6],
+            black: [0; 6],
+            white_occupied: 0,
+            black_occupied: 0,
+            all_occupied: 0,
+        };
+        for (index, &cell) in board.iter().enumerate() {
+            if let Some(piece) = cell {
+                let bit = 1u64 << index;
+                let kind = piece_kind_index(piece);
+                if piece.is_uppercase() {
+                    bb.white[kind] |= bit;
+                    bb.white_occupied |= bit;
+                } else {
+                    bb.black[kind] |= bit;
+                    bb.black_occupied |= bit;
                 }
-                true
-            } else {
-                false
             }
         }
-        'r' => {
-            if (dx == 0 && dy != 0) || (dy == 0 && dx != 0) {
-                let step_x = if dx == 0 { 0 } else { dx.signum() };
-                let step_y = if dy == 0 { 0 } else { dy.signum() };
-                let mut x = from.0 + step_x;
-                let mut y = from.1 + step_y;
-                while (x, y) != to {
-                    if get_piece_at(board, x, y).is_some() {
-                        return false;
-                    }
-                    x += step_x;
-                    y += step_y;
-                }
-                true
-            } else {
-                false
+        bb.all_occupied = bb.white_occupied | bb.black_occupied;
+        bb
+    }
+}
+
+/// --- Zobrist hashing for leaf deduplication ---
+///
+/// Because combinations are drawn with replacement and a mirrored pair can
+/// land on either candidate rank, distinct `search` paths can build the
+/// exact same board. `zobrist_hash` gives each board a near-unique u64 (one
+/// random key per piece-kind-per-color-per-square, XORed together) so
+/// `SeenBoards` can reject repeats in O(1) without ever materializing a FEN
+/// string for them.
+use std::sync::OnceLock;
+
+/// A minimal xorshift64* PRNG, seeded once to build the Zobrist key table.
+struct Xorshift64(u64);
+
+impl Xorshift64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+}
+
+/// One random key per (piece kind, color, square): indices 0..6 are White's
+/// P/N/B/R/Q/K, 6..12 are Black's.
+static ZOBRIST_KEYS: OnceLock<[[u64; 64]; 12]> = OnceLock::new();
+
+fn zobrist_keys() -> &'static [[u64; 64]; 12] {
+    ZOBRIST_KEYS.get_or_init(|| {
+        let mut rng = Xorshift64(0xD1620D7E6F9DB1A5);
+        let mut keys = [[0u64; 64]; 12];
+        for kind_keys in keys.iter_mut() {
+            for key in kind_keys.iter_mut() {
+                *key = rng.next_u64();
             }
         }
-        'q' => {
-            // Queen = rook + bishop.
-            if (dx.abs() == dy.abs() && dx != 0) {
-                let step_x = dx.signum();
-                let step_y = dy.signum();
-                let mut x = from.0 + step_x;
-                let mut y = from.1 + step_y;
-                while (x, y) != to {
-                    if get_piece_at(board, x, y).is_some() {
-                        return false;
-                    }
-                    x += step_x;
-                    y += step_y;
-                }
-                true
-            } else if (dx == 0 && dy != 0) || (dy == 0 && dx != 0) {
-                let step_x = if dx == 0 { 0 } else { dx.signum() };
-                let step_y = if dy == 0 { 0 } else { dy.signum() };
-                let mut x = from.0 + step_x;
-                let mut y = from.1 + step_y;
-                while (x, y) != to {
-                    if get_piece_at(board, x, y).is_some() {
-                        return false;
-                    }
-                    x += step_x;
-                    y += step_y;
-                }
-                true
-            } else {
-                false
-            }
+        keys
+    })
+}
+
+fn zobrist_hash(board: &BoardArray) -> u64 {
+    let keys = zobrist_keys();
+    let mut hash = 0u64;
+    for (square, &cell) in board.iter().enumerate() {
+        if let Some(piece) = cell {
+            let color_offset = if piece.is_uppercase() { 0 } else { 6 };
+            hash ^= keys[piece_kind_index(piece) + color_offset][square];
         }
-        _ => false,
     }
+    hash
 }
 
-/// Returns true if any piece on the board is attacked by an opponent.
-fn is_piece_under_attack(board: &BoardArray) -> bool {
-    for y in 0..8 {
-        for x in 0..8 {
-            let pos = (x as i32, y as i32);
-            if let Some(piece) = board[y * 8 + x] {
-                // For each enemy piece, test if it attacks pos.
-                for yy in 0..8 {
-                    for xx in 0..8 {
-                        let enemy_pos = (xx as i32, yy as i32);
-                        if let Some(op) = board[yy * 8 + xx] {
-                            if (piece.is_uppercase() && op.is_lowercase())
-                                || (piece.is_lowercase() && op.is_uppercase())
-                            {
-                                if piece_attacks(board, enemy_pos, pos, op) {
-                                    return true;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+/// Sharded hash-set of boards already seen, so concurrent `search` calls across
+/// the Rayon fan-out don't all contend on one lock.
+const SEEN_SHARDS: usize = 64;
+
+struct SeenBoards {
+    shards: Vec<Mutex<HashSet<u64>>>,
+}
+
+impl SeenBoards {
+    fn new() -> SeenBoards {
+        SeenBoards {
+            shards: (0..SEEN_SHARDS).map(|_| Mutex::new(HashSet::new())).collect(),
         }
     }
-    false
+
+    /// Returns true the first time `hash` is seen; false (and keeps nothing) on repeats.
+    fn insert(&self, hash: u64) -> bool {
+        let shard = &self.shards[(hash as usize) % SEEN_SHARDS];
+        shard.lock().unwrap().insert(hash)
+    }
+}
+
+fn knight_attacks_from(square: usize) -> Bitboard {
+    KNIGHT_ATTACKS[square]
+}
+
+fn king_attacks_from(square: usize) -> Bitboard {
+    KING_ATTACKS[square]
+}
+
+fn pawn_attacks_from(square: usize, white: bool) -> Bitboard {
+    if white {
+        WHITE_PAWN_ATTACKS[square]
+    } else {
+        BLACK_PAWN_ATTACKS[square]
+    }
+}
+
+/// Magic-bitboard slider lookup: mask the relevant occupancy, multiply by the
+/// square's magic number, and shift down to index straight into the square's
+/// slice of the flat attack table. No per-square ray walk is needed at all.
+fn magic_attacks_from(
+    square: usize,
+    occupied: Bitboard,
+    masks: &[Bitboard; 64],
+    magics: &[Bitboard; 64],
+    shifts: &[u32; 64],
+    offsets: &[usize; 64],
+    table: &[Bitboard],
+) -> Bitboard {
+    let relevant = occupied & masks[square];
+    let index = relevant.wrapping_mul(magics[square]) >> shifts[square];
+    table[offsets[square] + index as usize]
+}
+
+fn bishop_attacks_from(square: usize, occupied: Bitboard) -> Bitboard {
+    magic_attacks_from(
+        square, occupied, &BISHOP_MASKS, &BISHOP_MAGICS, &BISHOP_SHIFTS, &BISHOP_OFFSETS, &BISHOP_TABLE,
+    )
+}
+
+fn rook_attacks_from(square: usize, occupied: Bitboard) -> Bitboard {
+    magic_attacks_from(
+        square, occupied, &ROOK_MASKS, &ROOK_MAGICS, &ROOK_SHIFTS, &ROOK_OFFSETS, &ROOK_TABLE,
+    )
+}
+
+fn queen_attacks_from(square: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks_from(square, occupied) | rook_attacks_from(square, occupied)
+}
+
+/// Every attack delivered by one piece kind's bitboard, walking only its set bits.
+fn kind_attacks(mut bb: Bitboard, occupied: Bitboard, kind: usize, white: bool) -> Bitboard {
+    let mut attacks = 0;
+    while bb != 0 {
+        let square = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        attacks |= match kind {
+            PAWN => pawn_attacks_from(square, white),
+            KNIGHT => knight_attacks_from(square),
+            BISHOP => bishop_attacks_from(square, occupied),
+            ROOK => rook_attacks_from(square, occupied),
+            QUEEN => queen_attacks_from(square, occupied),
+            KING => king_attacks_from(square),
+            _ => panic!("Invalid piece kind"),
+        };
+    }
+    attacks
+}
+
+/// Union of every attack a given color's pieces deliver, given the board's full occupancy.
+/// Walks only the set bits of each piece's bitboard rather than scanning all 64 squares.
+fn color_attacks(pieces: &[Bitboard; 6], occupied: Bitboard, white: bool) -> Bitboard {
+    let mut attacks = 0;
+    for (kind, &bb) in pieces.iter().enumerate() {
+        attacks |= kind_attacks(bb, occupied, kind, white);
+    }
+    attacks
+}
+
+/// Returns true if any piece on the board is attacked by an opponent. Takes
+/// the board's already-derived `BitBoards` instead of rescanning the board.
+fn is_piece_under_attack(_board: &BoardArray, bb: &BitBoards) -> bool {
+    let white_attacks = color_attacks(&bb.white, bb.all_occupied, true);
+    let black_attacks = color_attacks(&bb.black, bb.all_occupied, false);
+    (white_attacks & bb.black_occupied) | (black_attacks & bb.white_occupied) != 0
+}
+
+/// Returns true if any non-king piece is "en prise": attacked by the
+/// opponent and defended by no piece of its own color. Narrower than
+/// `is_piece_under_attack`, which also rejects attacked-but-defended pieces.
+fn has_piece_en_prise(bb: &BitBoards) -> bool {
+    let white_attacks = color_attacks(&bb.white, bb.all_occupied, true);
+    let black_attacks = color_attacks(&bb.black, bb.all_occupied, false);
+    let white_non_king = bb.white_occupied & !bb.white[KING];
+    let black_non_king = bb.black_occupied & !bb.black[KING];
+    let white_en_prise = white_non_king & black_attacks & !white_attacks;
+    let black_en_prise = black_non_king & white_attacks & !black_attacks;
+    (white_en_prise | black_en_prise) != 0
 }
 
 /// --- White move generation ---
@@ -356,20 +627,24 @@ fn is_empty(board: &BoardArray, x: i32, y: i32) -> bool {
     }
 }
 
-/// Helper: test if the piece at (x,y) is an enemy relative to `piece`.
-fn is_enemy(board: &BoardArray, x: i32, y: i32, piece: char) -> bool {
-    if let Some(p) = get_piece_at(board, x, y) {
-        (piece.is_uppercase() && p.is_lowercase()) || (piece.is_lowercase() && p.is_uppercase())
-    } else {
-        false
+/// Turn a bitboard of reachable squares for a piece at (x,y) into `Move`s.
+fn moves_from_targets(x: i32, y: i32, piece: char, mut targets: Bitboard) -> Vec<Move> {
+    let mut moves = Vec::new();
+    while targets != 0 {
+        let to_square = targets.trailing_zeros() as i32;
+        targets &= targets - 1;
+        moves.push(Move { from: (x, y), to: (to_square % 8, to_square / 8), piece });
     }
+    moves
 }
 
-/// Generate pseudo–legal moves for a given white piece at (x,y).
-fn generate_moves_for_piece(board: &BoardArray, x: i32, y: i32, piece: char) -> Vec<Move> {
-    let mut moves = Vec::new();
+/// Generate pseudo–legal moves for a given white piece at (x,y), consulting
+/// the attack and magic tables instead of walking offset arrays square-by-square.
+fn generate_moves_for_piece(board: &BoardArray, bb: &BitBoards, x: i32, y: i32, piece: char) -> Vec<Move> {
+    let square = (y * 8 + x) as usize;
     match piece {
         'P' => {
+            let mut moves = Vec::new();
             // White pawn: forward move.
             if y + 1 < 8 && is_empty(board, x, y + 1) {
                 moves.push(Move { from: (x, y), to: (x, y + 1), piece });
@@ -378,117 +653,29 @@ fn generate_moves_for_piece(board: &BoardArray, x: i32, y: i32, piece: char) ->
                     moves.push(Move { from: (x, y), to: (x, y + 2), piece });
                 }
             }
-            // Captures.
-            for dx in [-1, 1].iter() {
-                let nx = x + dx;
-                let ny = y + 1;
-                if nx >= 0 && nx < 8 && ny < 8 && is_enemy(board, nx, ny, piece) {
-                    moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                }
-            }
-        }
-        'N' => {
-            let offsets = [
-                (2, 1), (1, 2), (-1, 2), (-2, 1),
-                (-2, -1), (-1, -2), (1, -2), (2, -1),
-            ];
-            for (dx, dy) in offsets.iter() {
-                let nx = x + dx;
-                let ny = y + dy;
-                if nx >= 0 && nx < 8 && ny >= 0 && ny < 8 {
-                    if get_piece_at(board, nx, ny).is_none() || is_enemy(board, nx, ny, piece) {
-                        moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                    }
-                }
-            }
-        }
-        'B' => {
-            let directions = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
-            for (dx, dy) in directions.iter() {
-                let mut nx = x + dx;
-                let mut ny = y + dy;
-                while nx >= 0 && nx < 8 && ny >= 0 && ny < 8 {
-                    if get_piece_at(board, nx, ny).is_none() {
-                        moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                    } else {
-                        if is_enemy(board, nx, ny, piece) {
-                            moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                        }
-                        break;
-                    }
-                    nx += dx;
-                    ny += dy;
-                }
-            }
-        }
-        'R' => {
-            let directions = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-            for (dx, dy) in directions.iter() {
-                let mut nx = x + dx;
-                let mut ny = y + dy;
-                while nx >= 0 && nx < 8 && ny >= 0 && ny < 8 {
-                    if get_piece_at(board, nx, ny).is_none() {
-                        moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                    } else {
-                        if is_enemy(board, nx, ny, piece) {
-                            moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                        }
-                        break;
-                    }
-                    nx += dx;
-                    ny += dy;
-                }
-            }
-        }
-        'Q' => {
-            let directions = [
-                (1, 0), (-1, 0), (0, 1), (0, -1),
-                (1, 1), (1, -1), (-1, 1), (-1, -1),
-            ];
-            for (dx, dy) in directions.iter() {
-                let mut nx = x + dx;
-                let mut ny = y + dy;
-                while nx >= 0 && nx < 8 && ny >= 0 && ny < 8 {
-                    if get_piece_at(board, nx, ny).is_none() {
-                        moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                    } else {
-                        if is_enemy(board, nx, ny, piece) {
-                            moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                        }
-                        break;
-                    }
-                    nx += dx;
-                    ny += dy;
-                }
-            }
+            // Captures, from the precomputed pawn attack table.
+            let captures = pawn_attacks_from(square, true) & bb.black_occupied;
+            moves.extend(moves_from_targets(x, y, piece, captures));
+            moves
         }
-        'K' => {
-            for dx in -1..=1 {
-                for dy in -1..=1 {
-                    if dx == 0 && dy == 0 { continue; }
-                    let nx = x + dx;
-                    let ny = y + dy;
-                    if nx >= 0 && nx < 8 && ny >= 0 && ny < 8 {
-                        if get_piece_at(board, nx, ny).is_none() || is_enemy(board, nx, ny, piece) {
-                            moves.push(Move { from: (x, y), to: (nx, ny), piece });
-                        }
-                    }
-                }
-            }
-        }
-        _ => {}
+        'N' => moves_from_targets(x, y, piece, knight_attacks_from(square) & !bb.white_occupied),
+        'B' => moves_from_targets(x, y, piece, bishop_attacks_from(square, bb.all_occupied) & !bb.white_occupied),
+        'R' => moves_from_targets(x, y, piece, rook_attacks_from(square, bb.all_occupied) & !bb.white_occupied),
+        'Q' => moves_from_targets(x, y, piece, queen_attacks_from(square, bb.all_occupied) & !bb.white_occupied),
+        'K' => moves_from_targets(x, y, piece, king_attacks_from(square) & !bb.white_occupied),
+        _ => Vec::new(),
     }
-    moves
 }
 
 /// Generate all pseudo–legal moves for White.
 fn generate_white_moves(board: &BoardArray) -> Vec<Move> {
+    let bb = BitBoards::from_board(board);
     let mut moves = Vec::new();
     for y in 0..8 {
         for x in 0..8 {
             if let Some(piece) = get_piece_at(board, x, y) {
                 if piece.is_uppercase() {
-                    moves.extend(generate_moves_for_piece(board, x, y, piece));
+                    moves.extend(generate_moves_for_piece(board, &bb, x, y, piece));
                 }
             }
         }
@@ -496,94 +683,113 @@ fn generate_white_moves(board: &BoardArray) -> Vec<Move> {
     moves
 }
 
-/// Make a move on a board copy.
-fn make_move(board: &BoardArray, mv: Move) -> BoardArray {
-    let mut new_board = *board;
+/// Minimal undo information for `unmake_move`: whatever sat on the
+/// destination square before the move, so it can be restored afterward.
+struct Undo {
+    captured: Option<char>,
+}
+
+/// Apply `mv` to `board` in place, updating `bb` to match incrementally
+/// (clearing the mover's origin bit, setting its destination bit, and
+/// clearing any captured piece's bit) instead of rescanning the whole board
+/// into a fresh `BitBoards`. Returns what's needed to undo it.
+fn make_move(board: &mut BoardArray, bb: &mut BitBoards, mv: Move) -> Undo {
     let from_index = (mv.from.1 as usize) * 8 + (mv.from.0 as usize);
     let to_index = (mv.to.1 as usize) * 8 + (mv.to.0 as usize);
-    new_board[from_index] = None;
-    new_board[to_index] = Some(mv.piece);
-    new_board
+    let captured = board[to_index];
+
+    let from_bit = 1u64 << from_index;
+    let to_bit = 1u64 << to_index;
+    let mover_kind = piece_kind_index(mv.piece);
+    if mv.piece.is_uppercase() {
+        bb.white[mover_kind] = (bb.white[mover_kind] & !from_bit) | to_bit;
+        bb.white_occupied = (bb.white_occupied & !from_bit) | to_bit;
+    } else {
+        bb.black[mover_kind] = (bb.black[mover_kind] & !from_bit) | to_bit;
+        bb.black_occupied = (bb.black_occupied & !from_bit) | to_bit;
+    }
+    if let Some(captured_piece) = captured {
+        let captured_kind = piece_kind_index(captured_piece);
+        if captured_piece.is_uppercase() {
+            bb.white[captured_kind] &= !to_bit;
+            bb.white_occupied &= !to_bit;
+        } else {
+            bb.black[captured_kind] &= !to_bit;
+            bb.black_occupied &= !to_bit;
+        }
+    }
+    bb.all_occupied = bb.white_occupied | bb.black_occupied;
+
+    board[from_index] = None;
+    board[to_index] = Some(mv.piece);
+    Undo { captured }
 }
 
-/// Find White’s king (if any) on the board.
-fn get_white_king(board: &BoardArray) -> Option<(i32, i32)> {
-    for y in 0..8 {
-        for x in 0..8 {
-            if let Some(piece) = get_piece_at(board, x, y) {
-                if piece == 'K' {
-                    return Some((x, y));
-                }
-            }
+/// Reverse `make_move`, restoring both `board` and `bb` to their state before `mv`.
+fn unmake_move(board: &mut BoardArray, bb: &mut BitBoards, mv: Move, undo: Undo) {
+    let from_index = (mv.from.1 as usize) * 8 + (mv.from.0 as usize);
+    let to_index = (mv.to.1 as usize) * 8 + (mv.to.0 as usize);
+
+    let from_bit = 1u64 << from_index;
+    let to_bit = 1u64 << to_index;
+    let mover_kind = piece_kind_index(mv.piece);
+    if mv.piece.is_uppercase() {
+        bb.white[mover_kind] = (bb.white[mover_kind] & !to_bit) | from_bit;
+        bb.white_occupied = (bb.white_occupied & !to_bit) | from_bit;
+    } else {
+        bb.black[mover_kind] = (bb.black[mover_kind] & !to_bit) | from_bit;
+        bb.black_occupied = (bb.black_occupied & !to_bit) | from_bit;
+    }
+    if let Some(captured_piece) = undo.captured {
+        let captured_kind = piece_kind_index(captured_piece);
+        if captured_piece.is_uppercase() {
+            bb.white[captured_kind] |= to_bit;
+            bb.white_occupied |= to_bit;
+        } else {
+            bb.black[captured_kind] |= to_bit;
+            bb.black_occupied |= to_bit;
         }
     }
-    None
+    bb.all_occupied = bb.white_occupied | bb.black_occupied;
+
+    board[from_index] = Some(mv.piece);
+    board[to_index] = undo.captured;
 }
 
 /// Return true if, after some white move (taken from the pseudo–legal list
 /// and after we discard moves that leave white king in check), White can deliver
-/// a check on the opposing king.
-fn can_deliver_check(board: &BoardArray) -> bool {
+/// a check on the opposing king. Reuses one board buffer and one `BitBoards`
+/// across every candidate move via make/unmake instead of cloning the board
+/// or rescanning it into a fresh `BitBoards` per move.
+fn can_deliver_check(board: &BoardArray, bb: &BitBoards) -> bool {
     let moves = generate_white_moves(board);
+    let mut work = *board;
+    let mut work_bb = *bb;
     for mv in moves {
-        let new_board = make_move(board, mv);
-        if let Some(_) = get_white_king(&new_board) {
-            if white_king_in_check(&new_board) {
-                continue;
-            }
-        }
-        if black_king_in_check(&new_board) {
+        let undo = make_move(&mut work, &mut work_bb, mv);
+        let delivers_check = !white_king_in_check(&work_bb) && black_king_in_check(&work_bb);
+        unmake_move(&mut work, &mut work_bb, mv, undo);
+        if delivers_check {
             return true;
         }
     }
     false
 }
 
-
 /// Check if White’s king is in check.
-fn white_king_in_check(board: &BoardArray) -> bool {
-    if let Some(pos) = get_white_king(board) {
-        for y in 0..8 {
-            for x in 0..8 {
-                if let Some(piece) = get_piece_at(board, x, y) {
-                    if piece.is_lowercase() && piece_attacks(board, (x, y), pos, piece) {
-                        return true;
-                    }
-                }
-            }
-        }
+fn white_king_in_check(bb: &BitBoards) -> bool {
+    if bb.white[KING] == 0 {
+        return false;
     }
-    false
+    color_attacks(&bb.black, bb.all_occupied, false) & bb.white[KING] != 0
 }
 
 /// Check if Black’s king is in check.
-fn black_king_in_check(board: &BoardArray) -> bool {
-    let mut king_pos = None;
-    for y in 0..8 {
-        for x in 0..8 {
-            if let Some(piece) = get_piece_at(board, x, y) {
-                if piece == 'k' {
-                    king_pos = Some((x, y));
-                    break;
-                }
-            }
-        }
-        if king_pos.is_some() {
-            break;
-        }
+fn black_king_in_check(bb: &BitBoards) -> bool {
+    if bb.black[KING] == 0 {
+        return false;
     }
-    if let Some(pos) = king_pos {
-        for y in 0..8 {
-            for x in 0..8 {
-                if let Some(piece) = get_piece_at(board, x, y) {
-                    if piece.is_uppercase() && piece_attacks(board, (x, y), pos, piece) {
-                        return true;
-                    }
-                }
-            }
-        }
-    }
-    false
+    color_attacks(&bb.white, bb.all_occupied, true) & bb.black[KING] != 0
 }
 
 /// Return true if at least one pawn (either color) is passed.
@@ -665,20 +871,146 @@ fn count_white_pawn_islands(board: &BoardArray) -> u32 {
 /// continuously writes received FEN strings to "stale_boards_6.fen". Then it uses Rayon to
 /// process all piece–type combinations in parallel. As soon as a valid board is found its FEN
 /// is sent (and written) immediately.
+/// Command-line configuration. All fields have the crate's historical
+/// defaults (6 pairs, `stale_boards_6.fen`, every filter, all cores), so
+/// `cargo run` with no arguments behaves exactly as before.
+struct Config {
+    num_pairs: usize,
+    output_path: String,
+    workers: Option<usize>,
+    max_pawn_islands: u32,
+    filter_names: Vec<String>,
+    verify_path: Option<String>,
+}
+
+impl Config {
+    fn default_() -> Config {
+        Config {
+            num_pairs: 6,
+            output_path: "stale_boards_6.fen".to_string(),
+            workers: None,
+            max_pawn_islands: 1,
+            filter_names: Vec::new(),
+            verify_path: None,
+        }
+    }
+
+    /// Parse `--pairs N`, `--output PATH`, `--workers N`, `--max-pawn-islands N`,
+    /// `--filters a,b,c` and `--verify PATH` from `args`. Unknown flags are a hard
+    /// error; `--list-filters` prints the available filter names and exits.
+    fn from_args(args: &[String]) -> Config {
+        let mut config = Config::default_();
+        let mut i = 0;
+        while i < args.len() {
+            let flag = &args[i];
+            let mut next = || {
+                i += 1;
+                args.get(i).unwrap_or_else(|| panic!("{flag} requires a value")).clone()
+            };
+            match flag.as_str() {
+                "--pairs" => config.num_pairs = next().parse().expect("--pairs expects an integer"),
+                "--output" => config.output_path = next(),
+                "--workers" => config.workers = Some(next().parse().expect("--workers expects an integer")),
+                "--max-pawn-islands" => {
+                    config.max_pawn_islands = next().parse().expect("--max-pawn-islands expects an integer")
+                }
+                "--filters" => {
+                    config.filter_names = next().split(',').map(|s| s.to_string()).collect();
+                }
+                "--verify" => config.verify_path = Some(next()),
+                "--list-filters" => {
+                    for name in FilterSet::default_with_threshold(1).names() {
+                        println!("{name}");
+                    }
+                    std::process::exit(0);
+                }
+                other => panic!("Unrecognized argument: {other}"),
+            }
+            i += 1;
+        }
+        config
+    }
+}
+
+/// Re-parse every line of a previously generated `.fen` file and re-check it
+/// against `filters`, reporting any line that fails to parse or no longer
+/// satisfies the stale criteria. Lines that still pass are counted but not
+/// printed, so a clean file produces one summary line.
+fn verify_fen_file(path: &str, filters: &FilterSet) {
+    let contents = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Unable to read {path}: {e}"));
+
+    let mut checked = 0usize;
+    let mut failed = 0usize;
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        checked += 1;
+        match fen_to_board(line) {
+            Ok(board) => {
+                // Same invariant `search` enforces before ever running `filters`.
+                let black_kings = board.iter().filter(|&&sq| sq == Some('k')).count();
+                if black_kings != 1 {
+                    failed += 1;
+                    println!(
+                        "line {}: expected exactly one black king, found {black_kings}: {line}",
+                        line_number + 1
+                    );
+                } else if let Some(reason) = filters.first_rejecting_name(&board) {
+                    failed += 1;
+                    println!("line {}: no longer stale ({reason}): {line}", line_number + 1);
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("line {}: {e}: {line}", line_number + 1);
+            }
+        }
+    }
+
+    println!("Verified {checked} position(s), {failed} failure(s)");
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
 fn main() {
-    let num_pairs = 6;
-    println!("Generating critical boards for {} mirrored pairs…", num_pairs);
-    
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = Config::from_args(&args);
+
+    if let Some(workers) = config.workers {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .build_global()
+            .expect("Failed to configure Rayon thread pool");
+    }
+
+    let filters =
+        FilterSet::default_with_threshold(config.max_pawn_islands).select(&config.filter_names);
+
+    if let Some(path) = &config.verify_path {
+        verify_fen_file(path, &filters);
+        return;
+    }
+
+    println!("Generating critical boards for {} mirrored pairs…", config.num_pairs);
+
     // Generate piece–type combinations.
-    let combinations = generate_combinations(num_pairs);
+    let combinations = generate_combinations(config.num_pairs);
 
     // Create a channel to send FEN strings.
     let (tx, rx) = mpsc::channel::<String>();
 
+    // Shared across every combination so transposed/mirrored duplicates are dropped.
+    let seen = SeenBoards::new();
+
     // Spawn a writer thread that writes FENs as they are received.
+    let output_path = config.output_path.clone();
     let writer_handle = thread::spawn(move || {
-        let file = std::fs::File::create("stale_boards_6.fen")
-            .expect("Unable to create stale_boards_6.fen");
+        let file = std::fs::File::create(&output_path)
+            .unwrap_or_else(|e| panic!("Unable to create {output_path}: {e}"));
         let mut writer = std::io::BufWriter::new(file);
         for fen in rx {
             writeln!(writer, "{}", fen).expect("Failed to write to file");
@@ -688,9 +1020,9 @@ fn main() {
     // Process combinations in parallel. Each thread gets its own clone of the sender.
     combinations.into_par_iter().for_each(|comb| {
         let local_tx = tx.clone();
-        process_combination(&comb, &local_tx);
+        process_combination(&comb, &local_tx, &seen, &filters);
     });
-    
+
     // Drop the original sender to signal completion.
     drop(tx);
     writer_handle.join().expect("Writer thread panicked");